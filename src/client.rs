@@ -6,6 +6,8 @@ use crate::response::*;
 use std::time::Duration;
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::SystemTime;
 
 use async_trait::async_trait;
@@ -15,12 +17,11 @@ use mockall::*;
 use openssl::hash::MessageDigest;
 use openssl::pkey::PKey;
 use openssl::sign::Signer;
+use reqwest::Method;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 const BASE_URL: &str = "https://coincheck.com";
-const MAX_RETRY_COUNT: i32 = 5;
-const RETRY_INTERVAL_MS: u64 = 10;
 
 #[async_trait]
 #[automock]
@@ -47,20 +48,405 @@ pub trait Client {
     async fn get_accounts_balance(&self) -> MyResult<HashMap<String, Balance>>;
 }
 
+/// Middleware stack that signs, retries and ultimately transports authenticated
+/// requests. Each layer implements [`middleware::Transport`] over an inner
+/// layer of the same trait, so a [`DefaultClient`] is just the stack
+/// `RetryMiddleware<NonceMiddleware<SignerMiddleware<ReqwestTransport>>>`, and
+/// callers are free to build their own stack (e.g. to insert a logging layer).
+pub mod middleware {
+    use super::*;
+
+    /// A request bound for [`Transport::call`]. Middlewares mutate `headers`
+    /// as the request flows inward; `method`/`url`/`body` never change.
+    #[derive(Debug, Clone)]
+    pub struct AuthRequest {
+        pub method: Method,
+        pub url: String,
+        pub body: Option<String>,
+        pub headers: HashMap<String, String>,
+    }
+
+    impl AuthRequest {
+        pub fn new(method: Method, url: &str, body: Option<String>) -> AuthRequest {
+            AuthRequest {
+                method,
+                url: url.to_owned(),
+                body,
+                headers: HashMap::new(),
+            }
+        }
+    }
+
+    /// The raw result of a [`Transport::call`]: the HTTP status alongside the
+    /// response body, so a [`RetryPolicy`] can judge retryability (e.g. a 429
+    /// or 5xx) without having to know how to parse the body itself.
+    #[derive(Debug, Clone)]
+    pub struct TransportResponse {
+        pub status: reqwest::StatusCode,
+        pub text: String,
+    }
+
+    /// The interface every layer of the stack implements: takes a request and
+    /// returns the raw response. The innermost implementation,
+    /// [`ReqwestTransport`], issues the HTTP request as-is, with no knowledge
+    /// of signing, nonces or retries.
+    #[async_trait]
+    pub trait Transport: Send + Sync {
+        async fn call(&self, req: AuthRequest) -> MyResult<TransportResponse>;
+    }
+
+    /// Sends the request over `reqwest`, attaching whatever headers and body
+    /// the outer middlewares have already assembled.
+    #[derive(Debug)]
+    pub struct ReqwestTransport {
+        client: reqwest::Client,
+    }
+
+    impl ReqwestTransport {
+        pub fn new(client: reqwest::Client) -> ReqwestTransport {
+            ReqwestTransport { client }
+        }
+    }
+
+    #[async_trait]
+    impl Transport for ReqwestTransport {
+        async fn call(&self, req: AuthRequest) -> MyResult<TransportResponse> {
+            let mut builder = self.client.request(req.method, &req.url);
+            for (k, v) in &req.headers {
+                builder = builder.header(k, v);
+            }
+            if let Some(body) = req.body {
+                builder = builder
+                    .header("Content-Type", "application/json")
+                    .body(body);
+            }
+            let res = builder.send().await?;
+            let status = res.status();
+            let text = res.text().await?;
+            Ok(TransportResponse { status, text })
+        }
+    }
+
+    /// Injects `ACCESS-KEY`/`ACCESS-SIGNATURE`, computed from the nonce the
+    /// outer [`NonceMiddleware`] has already attached to the request.
+    #[derive(Debug)]
+    pub struct SignerMiddleware<T: Transport> {
+        inner: T,
+        access_key: String,
+        secret_key: String,
+    }
+
+    impl<T: Transport> SignerMiddleware<T> {
+        pub fn new(inner: T, access_key: &str, secret_key: &str) -> SignerMiddleware<T> {
+            SignerMiddleware {
+                inner,
+                access_key: access_key.to_string(),
+                secret_key: secret_key.to_string(),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl<T: Transport> Transport for SignerMiddleware<T> {
+        async fn call(&self, mut req: AuthRequest) -> MyResult<TransportResponse> {
+            let nonce = match req.headers.get("ACCESS-NONCE") {
+                Some(nonce) => nonce.clone(),
+                None => {
+                    return Err(Box::new(ParseError(
+                        "ACCESS-NONCE header missing; SignerMiddleware must be wrapped by a \
+                         NonceMiddleware"
+                            .to_owned(),
+                    )))
+                }
+            };
+            let body = req.body.clone().unwrap_or_default();
+            let signature = make_signature(&nonce, &req.url, &body, &self.secret_key);
+
+            req.headers
+                .insert("ACCESS-KEY".to_owned(), self.access_key.clone());
+            req.headers.insert("ACCESS-SIGNATURE".to_owned(), signature);
+
+            self.inner.call(req).await
+        }
+    }
+
+    /// Hands out strictly increasing nonces from an in-memory counter rather
+    /// than the wall clock, so two requests issued within the same
+    /// millisecond (or concurrently from multiple tasks) never collide.
+    /// Seeded to the current epoch-millis at construction so nonces stay
+    /// compatible with Coincheck's "must be increasing" requirement across
+    /// process restarts.
+    #[derive(Debug)]
+    pub struct NonceManager {
+        counter: AtomicU64,
+    }
+
+    impl NonceManager {
+        pub fn new() -> MyResult<NonceManager> {
+            Ok(NonceManager {
+                counter: AtomicU64::new(current_millis()?),
+            })
+        }
+
+        /// Returns the next nonce, strictly greater than every nonce handed
+        /// out before it.
+        pub fn next(&self) -> u64 {
+            self.counter.fetch_add(1, Ordering::SeqCst)
+        }
+
+        /// Forces the counter past both the current wall clock and the last
+        /// handed-out value. Used after a "Nonce must be incremented"
+        /// rejection, which can happen if the server has already seen a
+        /// higher nonce than this process's counter (e.g. from a previous
+        /// run).
+        pub fn bump_past_current_millis(&self) -> MyResult<()> {
+            let millis = current_millis()?;
+            self.counter
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |stored| {
+                    Some(std::cmp::max(millis, stored + 1))
+                })
+                .ok();
+            Ok(())
+        }
+    }
+
+    fn current_millis() -> MyResult<u64> {
+        let millis = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_millis() as u64;
+        Ok(millis)
+    }
+
+    /// Owns nonce assignment, attaching `ACCESS-NONCE` to the request before
+    /// it reaches the [`SignerMiddleware`]. Holds the [`NonceManager`] behind
+    /// an `Arc` so it can be shared with a [`RetryMiddleware`] above it.
+    #[derive(Debug)]
+    pub struct NonceMiddleware<T: Transport> {
+        inner: T,
+        nonce_manager: Arc<NonceManager>,
+    }
+
+    impl<T: Transport> NonceMiddleware<T> {
+        pub fn new(inner: T, nonce_manager: Arc<NonceManager>) -> NonceMiddleware<T> {
+            NonceMiddleware {
+                inner,
+                nonce_manager,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl<T: Transport> Transport for NonceMiddleware<T> {
+        async fn call(&self, mut req: AuthRequest) -> MyResult<TransportResponse> {
+            let nonce = self.nonce_manager.next();
+            req.headers
+                .insert("ACCESS-NONCE".to_owned(), nonce.to_string());
+            self.inner.call(req).await
+        }
+    }
+
+    /// Tunables for [`RetryMiddleware`]'s backoff. Each attempt waits
+    /// `min(max_delay, base_delay * 2^attempt)` plus a random jitter fraction
+    /// of that delay, so concurrent callers retrying the same failure don't
+    /// all hammer the API again at once.
+    #[derive(Debug, Clone)]
+    pub struct RetryConfig {
+        pub max_retries: u32,
+        pub base_delay: Duration,
+        pub max_delay: Duration,
+        pub jitter: f64,
+    }
+
+    impl Default for RetryConfig {
+        fn default() -> RetryConfig {
+            RetryConfig {
+                max_retries: 5,
+                base_delay: Duration::from_millis(10),
+                max_delay: Duration::from_secs(5),
+                jitter: 0.1,
+            }
+        }
+    }
+
+    impl RetryConfig {
+        pub(crate) fn delay_for(&self, attempt: u32) -> Duration {
+            let exp = self.base_delay.saturating_mul(1u32 << attempt.min(31));
+            let capped = std::cmp::min(exp, self.max_delay);
+            capped.mul_f64(1.0 + self.jitter * jitter_fraction())
+        }
+    }
+
+    /// A cheap, dependency-free source of jitter: the sub-millisecond part of
+    /// the current time. It only needs to decorrelate concurrent retries, not
+    /// be cryptographically random.
+    fn jitter_fraction() -> f64 {
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        (nanos % 1_000_000) as f64 / 1_000_000.0
+    }
+
+    /// Decides whether a [`RetryMiddleware`] should retry a given outcome.
+    /// The default policy, [`DefaultRetryPolicy`], retries Coincheck's "Nonce
+    /// must be incremented" rejection as well as HTTP 429/5xx and transport
+    /// errors; callers can plug in a policy of their own instead.
+    ///
+    /// [`RetryMiddleware`] only ever consults this policy for idempotent
+    /// methods (GET, DELETE) — for a non-idempotent method like POST it is
+    /// never safe to silently resubmit on a 429/5xx/transport error, since
+    /// the original request may have already executed server-side. The
+    /// nonce-rejection case is handled separately, unconditionally, because
+    /// it is known to precede any side effect.
+    pub trait RetryPolicy: Send + Sync {
+        fn should_retry_response(&self, response: &TransportResponse) -> bool;
+
+        /// Called when the transport call itself failed (e.g. a connection
+        /// error). Defaults to not retrying, since most `Transport` errors
+        /// stem from programmer error (bad URL) rather than a transient
+        /// network hiccup.
+        fn should_retry_error(&self) -> bool {
+            false
+        }
+    }
+
+    /// Retries on the nonce-rejection message, plus HTTP 429 and 5xx
+    /// responses and any transport-level error. Only applies to idempotent
+    /// requests — see the note on [`RetryPolicy`].
+    #[derive(Debug, Default)]
+    pub struct DefaultRetryPolicy;
+
+    impl RetryPolicy for DefaultRetryPolicy {
+        fn should_retry_response(&self, response: &TransportResponse) -> bool {
+            if response.status.as_u16() == 429 || response.status.is_server_error() {
+                return true;
+            }
+            match serde_json::from_str::<ErrorResponse>(&response.text) {
+                Ok(res) => res.error == "Nonce must be incremented",
+                Err(_) => false,
+            }
+        }
+
+        fn should_retry_error(&self) -> bool {
+            true
+        }
+    }
+
+    /// Owns the retry loop: on a retryable outcome it re-issues the whole
+    /// request (so the inner [`NonceMiddleware`]/[`SignerMiddleware`] refresh
+    /// the nonce and signature on every attempt) instead of returning. Shares
+    /// the same [`NonceManager`] as the [`NonceMiddleware`] further down the
+    /// stack so it can force the counter ahead of the clock when the API
+    /// still rejects a nonce as stale.
+    pub struct RetryMiddleware<T: Transport> {
+        inner: T,
+        nonce_manager: Arc<NonceManager>,
+        config: RetryConfig,
+        policy: Arc<dyn RetryPolicy>,
+    }
+
+    impl<T: Transport> RetryMiddleware<T> {
+        pub fn new(inner: T, nonce_manager: Arc<NonceManager>) -> RetryMiddleware<T> {
+            Self::with_config(inner, nonce_manager, RetryConfig::default())
+        }
+
+        pub fn with_config(
+            inner: T,
+            nonce_manager: Arc<NonceManager>,
+            config: RetryConfig,
+        ) -> RetryMiddleware<T> {
+            Self::with_policy(inner, nonce_manager, config, Arc::new(DefaultRetryPolicy))
+        }
+
+        pub fn with_policy(
+            inner: T,
+            nonce_manager: Arc<NonceManager>,
+            config: RetryConfig,
+            policy: Arc<dyn RetryPolicy>,
+        ) -> RetryMiddleware<T> {
+            RetryMiddleware {
+                inner,
+                nonce_manager,
+                config,
+                policy,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl<T: Transport> Transport for RetryMiddleware<T> {
+        async fn call(&self, req: AuthRequest) -> MyResult<TransportResponse> {
+            // A retry re-signs with a fresh nonce, so for a non-idempotent
+            // method (POST) a retry after a 5xx/transport error is a brand
+            // new request, not a safe replay — the original may have already
+            // executed server-side. Only the nonce-rejection case is exempt:
+            // it's returned before the request has any side effect, so it's
+            // safe to retry regardless of method.
+            let is_idempotent = req.method == Method::GET || req.method == Method::DELETE;
+
+            let mut attempt: u32 = 0;
+            loop {
+                let outcome = self.inner.call(req.clone()).await;
+
+                let should_retry = match &outcome {
+                    Ok(response) => {
+                        Self::is_pre_execution_nonce_rejection(response)
+                            || (is_idempotent && self.policy.should_retry_response(response))
+                    }
+                    Err(_) => is_idempotent && self.policy.should_retry_error(),
+                };
+
+                if !should_retry || attempt >= self.config.max_retries {
+                    return outcome;
+                }
+
+                attempt += 1;
+                warn!(
+                    "request failed, retrying attempt:{} of max:{}",
+                    attempt, self.config.max_retries,
+                );
+                self.nonce_manager.bump_past_current_millis()?;
+                tokio::time::sleep(self.config.delay_for(attempt)).await;
+            }
+        }
+    }
+
+    impl<T: Transport> RetryMiddleware<T> {
+        /// True only for Coincheck's "Nonce must be incremented" rejection,
+        /// which happens before the request has any side effect and so is
+        /// always safe to retry, even for a non-idempotent method like POST.
+        fn is_pre_execution_nonce_rejection(response: &TransportResponse) -> bool {
+            match serde_json::from_str::<ErrorResponse>(&response.text) {
+                Ok(res) => res.error == "Nonce must be incremented",
+                Err(_) => false,
+            }
+        }
+    }
+}
+
+use middleware::{
+    AuthRequest, DefaultRetryPolicy, NonceManager, NonceMiddleware, ReqwestTransport, RetryConfig,
+    RetryMiddleware, RetryPolicy, SignerMiddleware, Transport, TransportResponse,
+};
+
+/// The default middleware stack: retry around nonce assignment around
+/// signing around a plain `reqwest` transport.
+type DefaultStack = RetryMiddleware<NonceMiddleware<SignerMiddleware<ReqwestTransport>>>;
+
 #[derive(Debug)]
-pub struct DefaultClient {
-    client: reqwest::Client,
-    access_key: String,
-    secret_key: String,
+pub struct DefaultClient<T: Transport = DefaultStack> {
+    http: reqwest::Client,
+    base_url: String,
+    transport: T,
 }
 
 #[async_trait]
-impl Client for DefaultClient {
+impl<T: Transport> Client for DefaultClient<T> {
     async fn get_ticker(&self, pair: &str) -> MyResult<Ticker> {
-        let url = format!("{}{}", BASE_URL, "/api/ticker");
+        let url = format!("{}{}", self.base_url, "/api/ticker");
         let params = [("pair", pair)];
         let body = self
-            .client
+            .http
             .get(&url)
             .query(&params)
             .send()
@@ -71,10 +457,10 @@ impl Client for DefaultClient {
     }
 
     async fn get_order_books(&self, pair: &str) -> MyResult<OrderBooks> {
-        let url = format!("{}{}", BASE_URL, "/api/order_books");
+        let url = format!("{}{}", self.base_url, "/api/order_books");
         let params = [("pair", pair)];
         let body = self
-            .client
+            .http
             .get(&url)
             .query(&params)
             .send()
@@ -90,7 +476,7 @@ impl Client for DefaultClient {
         pair: &str,
         amount: f64,
     ) -> MyResult<f64> {
-        let url = format!("{}{}", BASE_URL, "/api/exchange/orders/rate");
+        let url = format!("{}{}", self.base_url, "/api/exchange/orders/rate");
         let amount_str = format!("{:.3}", amount);
         let params = [
             (
@@ -106,7 +492,7 @@ impl Client for DefaultClient {
             ("amount", &amount_str),
         ];
         let body = self
-            .client
+            .http
             .get(&url)
             .query(&params)
             .send()
@@ -118,11 +504,15 @@ impl Client for DefaultClient {
     }
 
     async fn post_exchange_orders(&self, req: &NewOrder) -> MyResult<Order> {
-        let url = format!("{}{}", BASE_URL, "/api/exchange/orders");
+        let url = format!("{}{}", self.base_url, "/api/exchange/orders");
         let req_body = OrdersPostRequest::new(req)?;
 
         let res = self
-            .post_request_with_auth::<OrdersPostRequest, OrdersPostResponse>(&url, req_body)
+            .request_with_auth::<OrdersPostRequest, OrdersPostResponse>(
+                Method::POST,
+                &url,
+                Some(req_body),
+            )
             .await?;
         if res.success {
             Ok(res.to_model()?)
@@ -136,9 +526,9 @@ impl Client for DefaultClient {
     }
 
     async fn get_exchange_orders_opens(&self) -> MyResult<Vec<OpenOrder>> {
-        let url = format!("{}{}", BASE_URL, "/api/exchange/orders/opens");
+        let url = format!("{}{}", self.base_url, "/api/exchange/orders/opens");
         let body = self
-            .get_request_with_auth::<OrdersOpensGetResponse>(&url)
+            .request_with_auth::<(), OrdersOpensGetResponse>(Method::GET, &url, None)
             .await?;
         let mut res: Vec<OpenOrder> = Vec::new();
         for o in body.orders {
@@ -149,9 +539,9 @@ impl Client for DefaultClient {
     }
 
     async fn delete_exchange_orders(&self, id: u64) -> MyResult<u64> {
-        let url = format!("{}{}{}", BASE_URL, "/api/exchange/orders/", id);
+        let url = format!("{}{}{}", self.base_url, "/api/exchange/orders/", id);
         let body = self
-            .delete_request_with_auth::<OrdersDeleteResponse>(&url)
+            .request_with_auth::<(), OrdersDeleteResponse>(Method::DELETE, &url, None)
             .await?;
         Ok(body.id)
     }
@@ -159,181 +549,166 @@ impl Client for DefaultClient {
     async fn get_exchange_orders_cancel_status(&self, id: u64) -> MyResult<bool> {
         let url: String = format!(
             "{}{}{}",
-            BASE_URL, "/api/exchange/orders/cancel_status?id=", id
+            self.base_url, "/api/exchange/orders/cancel_status?id=", id
         );
         let body = self
-            .get_request_with_auth::<OrdersCancelStatusGetResponse>(&url)
+            .request_with_auth::<(), OrdersCancelStatusGetResponse>(Method::GET, &url, None)
             .await?;
         Ok(body.cancel)
     }
 
     async fn get_accounts_balance(&self) -> MyResult<HashMap<String, Balance>> {
-        let url: String = format!("{}{}", BASE_URL, "/api/accounts/balance");
+        let url: String = format!("{}{}", self.base_url, "/api/accounts/balance");
         let body = self
-            .get_request_with_auth::<BalanceGetResponse>(&url)
+            .request_with_auth::<(), BalanceGetResponse>(Method::GET, &url, None)
             .await?;
         Ok(body.to_map()?)
     }
 }
 
-impl DefaultClient {
-    pub fn new(access_key: &str, secret_key: &str) -> MyResult<DefaultClient> {
-        let client = reqwest::Client::builder().build()?;
-        Ok(DefaultClient {
-            client: client,
-            access_key: access_key.to_string(),
-            secret_key: secret_key.to_string(),
-        })
+impl DefaultClient<DefaultStack> {
+    /// Builds the default stack: retry around nonce assignment around
+    /// HMAC signing around a bare `reqwest::Client` pointed at
+    /// `https://coincheck.com`. Use [`DefaultClientBuilder`] to customize the
+    /// base URL, timeouts, retry behavior, or to inject a preconfigured
+    /// `reqwest::Client`.
+    pub fn new(access_key: &str, secret_key: &str) -> MyResult<DefaultClient<DefaultStack>> {
+        DefaultClientBuilder::new(access_key, secret_key).build()
     }
+}
 
-    async fn get_request_with_auth<T: DeserializeOwned>(&self, url: &str) -> MyResult<T> {
-        let mut retry_count: i32 = 0;
-        loop {
-            let nonce = SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)?
-                .as_millis();
-            let signature = make_signature(nonce, &url, "", &self.secret_key);
-
-            let res_text = self
-                .client
-                .get(url)
-                .header("ACCESS-KEY", &self.access_key)
-                .header("ACCESS-NONCE", format!("{}", nonce))
-                .header("ACCESS-SIGNATURE", signature)
-                .send()
-                .await?
-                .text()
-                .await?;
-
-            if let Ok(res) = serde_json::from_str::<T>(&res_text) {
-                return Ok(res);
-            }
-            if let Ok(res) = serde_json::from_str::<ErrorResponse>(&res_text) {
-                if DefaultClient::should_retry(&res) {
-                    retry_count += 1;
-                    if retry_count <= MAX_RETRY_COUNT {
-                        warn!(
-                            "response is error, retry request retry_count:{} <= max:{}, error:{}",
-                            retry_count, MAX_RETRY_COUNT, res.error,
-                        );
-                        let d = Duration::from_millis(RETRY_INTERVAL_MS);
-                        std::thread::sleep(d);
-                        continue;
-                    }
-                }
-                return Err(Box::new(ResponseError {
-                    message: res.error,
-                    url: url.to_owned(),
-                    request: "".to_owned(),
-                }));
-            }
-            return Err(Box::new(ParseError(res_text)));
+impl<T: Transport> DefaultClient<T> {
+    /// Builds a client around a caller-supplied transport stack, e.g. one
+    /// with a custom logging/metrics layer spliced in.
+    pub fn with_transport(http: reqwest::Client, base_url: &str, transport: T) -> DefaultClient<T> {
+        DefaultClient {
+            http,
+            base_url: base_url.to_owned(),
+            transport,
         }
     }
 
-    async fn post_request_with_auth<T, U>(&self, url: &str, body: T) -> MyResult<U>
+    async fn request_with_auth<B, R>(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<B>,
+    ) -> MyResult<R>
     where
-        T: Serialize,
-        U: DeserializeOwned,
+        B: Serialize,
+        R: DeserializeOwned,
     {
-        let mut retry_count: i32 = 0;
-        loop {
-            let nonce = SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)?
-                .as_millis();
-            let json = serde_json::to_string(&body)?;
-            let signature = make_signature(nonce, &url, &json, &self.secret_key);
-
-            let res_text = self
-                .client
-                .post(url)
-                .header("Content-Type", "application/json")
-                .header("ACCESS-KEY", &self.access_key)
-                .header("ACCESS-NONCE", format!("{}", nonce))
-                .header("ACCESS-SIGNATURE", signature)
-                .body(json.clone())
-                .send()
-                .await?
-                .text()
-                .await?;
-
-            if let Ok(res) = serde_json::from_str::<U>(&res_text) {
-                return Ok(res);
-            }
-            if let Ok(res) = serde_json::from_str::<ErrorResponse>(&res_text) {
-                if DefaultClient::should_retry(&res) {
-                    retry_count += 1;
-                    if retry_count <= MAX_RETRY_COUNT {
-                        warn!(
-                            "response is error, retry request retry_count:{} <= max:{}, error:{}",
-                            retry_count, MAX_RETRY_COUNT, res.error,
-                        );
-                        let d = Duration::from_millis(RETRY_INTERVAL_MS);
-                        std::thread::sleep(d);
-                        continue;
-                    }
-                }
-                return Err(Box::new(ResponseError {
-                    message: res.error,
-                    url: url.to_owned(),
-                    request: json,
-                }));
-            }
-            return Err(Box::new(ParseError(res_text)));
+        let json = body.map(|b| serde_json::to_string(&b)).transpose()?;
+        let req = AuthRequest::new(method, url, json.clone());
+        let res = self.transport.call(req).await?;
+        Self::parse_response(url, &json.unwrap_or_default(), res.text)
+    }
+
+    fn parse_response<R: DeserializeOwned>(
+        url: &str,
+        request: &str,
+        res_text: String,
+    ) -> MyResult<R> {
+        if let Ok(res) = serde_json::from_str::<R>(&res_text) {
+            return Ok(res);
+        }
+        if let Ok(res) = serde_json::from_str::<ErrorResponse>(&res_text) {
+            return Err(Box::new(ResponseError {
+                message: res.error,
+                url: url.to_owned(),
+                request: request.to_owned(),
+            }));
         }
+        Err(Box::new(ParseError(res_text)))
     }
+}
 
-    async fn delete_request_with_auth<T: DeserializeOwned>(&self, url: &str) -> MyResult<T> {
-        let mut retry_count: i32 = 0;
-        loop {
-            let nonce = SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)?
-                .as_millis();
-            let signature = make_signature(nonce, &url, "", &self.secret_key);
-
-            let res_text = self
-                .client
-                .delete(url)
-                .header("ACCESS-KEY", &self.access_key)
-                .header("ACCESS-NONCE", format!("{}", nonce))
-                .header("ACCESS-SIGNATURE", signature)
-                .send()
-                .await?
-                .text()
-                .await?;
-
-            if let Ok(res) = serde_json::from_str::<T>(&res_text) {
-                return Ok(res);
-            }
-            if let Ok(res) = serde_json::from_str::<ErrorResponse>(&res_text) {
-                if DefaultClient::should_retry(&res) {
-                    retry_count += 1;
-                    if retry_count <= MAX_RETRY_COUNT {
-                        warn!(
-                            "response is error, retry request retry_count:{} <= max:{}, error:{}",
-                            retry_count, MAX_RETRY_COUNT, res.error,
-                        );
-                        let d = Duration::from_millis(RETRY_INTERVAL_MS);
-                        std::thread::sleep(d);
-                        continue;
-                    }
-                }
-                return Err(Box::new(ResponseError {
-                    message: res.error,
-                    url: url.to_owned(),
-                    request: "".to_owned(),
-                }));
-            }
-            return Err(Box::new(ParseError(res_text)));
+/// Builds a [`DefaultClient`] with the default middleware stack, letting
+/// callers override the base URL, timeouts, retry behavior, or supply their
+/// own `reqwest::Client` (e.g. to point at a local mock server in tests).
+pub struct DefaultClientBuilder {
+    access_key: String,
+    secret_key: String,
+    base_url: String,
+    http_client: Option<reqwest::Client>,
+    request_timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    retry_config: RetryConfig,
+}
+
+impl DefaultClientBuilder {
+    pub fn new(access_key: &str, secret_key: &str) -> DefaultClientBuilder {
+        DefaultClientBuilder {
+            access_key: access_key.to_string(),
+            secret_key: secret_key.to_string(),
+            base_url: BASE_URL.to_string(),
+            http_client: None,
+            request_timeout: None,
+            connect_timeout: None,
+            retry_config: RetryConfig::default(),
         }
     }
 
-    fn should_retry(res: &ErrorResponse) -> bool {
-        res.error == "Nonce must be incremented"
+    pub fn base_url(mut self, base_url: &str) -> DefaultClientBuilder {
+        self.base_url = base_url.to_owned();
+        self
+    }
+
+    pub fn request_timeout(mut self, timeout: Duration) -> DefaultClientBuilder {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> DefaultClientBuilder {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Supplies a preconfigured `reqwest::Client` instead of letting the
+    /// builder construct one from `request_timeout`/`connect_timeout`.
+    pub fn http_client(mut self, http_client: reqwest::Client) -> DefaultClientBuilder {
+        self.http_client = Some(http_client);
+        self
+    }
+
+    pub fn retry_config(mut self, retry_config: RetryConfig) -> DefaultClientBuilder {
+        self.retry_config = retry_config;
+        self
+    }
+
+    pub fn build(self) -> MyResult<DefaultClient<DefaultStack>> {
+        let http = match self.http_client {
+            Some(http_client) => http_client,
+            None => {
+                let mut builder = reqwest::Client::builder();
+                if let Some(timeout) = self.request_timeout {
+                    builder = builder.timeout(timeout);
+                }
+                if let Some(timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(timeout);
+                }
+                builder.build()?
+            }
+        };
+
+        let nonce_manager = Arc::new(NonceManager::new()?);
+        let signer = SignerMiddleware::new(
+            ReqwestTransport::new(http.clone()),
+            &self.access_key,
+            &self.secret_key,
+        );
+        let nonce = NonceMiddleware::new(signer, nonce_manager.clone());
+        let transport = RetryMiddleware::with_config(nonce, nonce_manager, self.retry_config);
+
+        Ok(DefaultClient {
+            http,
+            base_url: self.base_url,
+            transport,
+        })
     }
 }
 
-fn make_signature(nonce: u128, url: &str, body: &str, secret_key: &str) -> String {
+fn make_signature(nonce: &str, url: &str, body: &str, secret_key: &str) -> String {
     let key = PKey::hmac(secret_key.as_bytes()).unwrap();
     let mut signer = Signer::new(MessageDigest::sha256(), &key).unwrap();
     let v = format!("{}{}{}", nonce, url, body);
@@ -350,8 +725,194 @@ mod tests {
     #[test]
     fn test_make_signature() {
         assert_eq!(
-            make_signature(12345, "https://example.com", "hoge=foo", "abcdefg"),
+            make_signature("12345", "https://example.com", "hoge=foo", "abcdefg"),
             "65a5d4bf76d4266e2f56582c31ca3e9ac163c80745e84357ead5a2899a37e218"
         );
     }
+
+    /// A stub [`Transport`] that just echoes back an empty 200, so tests can
+    /// exercise a middleware in isolation without a real inner transport.
+    struct NoopTransport;
+
+    #[async_trait]
+    impl Transport for NoopTransport {
+        async fn call(&self, _req: AuthRequest) -> MyResult<TransportResponse> {
+            Ok(TransportResponse {
+                status: reqwest::StatusCode::OK,
+                text: "{}".to_owned(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signer_middleware_errors_when_access_nonce_missing() {
+        let signer = SignerMiddleware::new(NoopTransport, "access-key", "secret-key");
+        let req = AuthRequest::new(Method::GET, "https://example.com", None);
+
+        let result = signer.call(req).await;
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_nonce_manager_next_is_strictly_increasing() -> MyResult<()> {
+        let manager = NonceManager::new()?;
+        let first = manager.next();
+        let second = manager.next();
+        let third = manager.next();
+        assert!(first < second);
+        assert!(second < third);
+        Ok(())
+    }
+
+    #[test]
+    fn test_nonce_manager_bump_past_current_millis_moves_counter_forward() -> MyResult<()> {
+        let manager = NonceManager::new()?;
+        let before = manager.next();
+        manager.bump_past_current_millis()?;
+        let after = manager.next();
+        assert!(after > before);
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_config_delay_for_respects_max_delay() {
+        let config = RetryConfig {
+            max_retries: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(250),
+            jitter: 0.0,
+        };
+        assert_eq!(config.delay_for(1), Duration::from_millis(200));
+        assert_eq!(config.delay_for(3), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_default_retry_policy_should_retry_response() {
+        let policy = DefaultRetryPolicy;
+
+        let too_many_requests = TransportResponse {
+            status: reqwest::StatusCode::TOO_MANY_REQUESTS,
+            text: "{}".to_owned(),
+        };
+        assert!(policy.should_retry_response(&too_many_requests));
+
+        let server_error = TransportResponse {
+            status: reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+            text: "{}".to_owned(),
+        };
+        assert!(policy.should_retry_response(&server_error));
+
+        let nonce_error = TransportResponse {
+            status: reqwest::StatusCode::OK,
+            text: r#"{"success":false,"error":"Nonce must be incremented"}"#.to_owned(),
+        };
+        assert!(policy.should_retry_response(&nonce_error));
+
+        let other_error = TransportResponse {
+            status: reqwest::StatusCode::OK,
+            text: r#"{"success":false,"error":"something else"}"#.to_owned(),
+        };
+        assert!(!policy.should_retry_response(&other_error));
+    }
+
+    /// A stub [`Transport`] that always returns an HTTP 500 and counts how
+    /// many times it was called, so tests can assert on retry behavior
+    /// without a real server.
+    struct AlwaysServerError {
+        calls: Arc<AtomicU32>,
+    }
+
+    #[async_trait]
+    impl Transport for AlwaysServerError {
+        async fn call(&self, _req: AuthRequest) -> MyResult<TransportResponse> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(TransportResponse {
+                status: reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                text: "{}".to_owned(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_middleware_does_not_retry_server_error_for_post() -> MyResult<()> {
+        let calls = Arc::new(AtomicU32::new(0));
+        let transport = AlwaysServerError {
+            calls: calls.clone(),
+        };
+        let nonce_manager = Arc::new(NonceManager::new()?);
+        let retry = RetryMiddleware::new(transport, nonce_manager);
+
+        let req = AuthRequest::new(Method::POST, "http://example.invalid", None);
+        retry.call(req).await?;
+
+        // POST creates an order; re-signing and resubmitting after a 5xx
+        // could duplicate it, so it must not be retried.
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retry_middleware_retries_server_error_for_get() -> MyResult<()> {
+        let calls = Arc::new(AtomicU32::new(0));
+        let transport = AlwaysServerError {
+            calls: calls.clone(),
+        };
+        let nonce_manager = Arc::new(NonceManager::new()?);
+        let config = RetryConfig {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(2),
+            jitter: 0.0,
+        };
+        let retry = RetryMiddleware::with_config(transport, nonce_manager, config);
+
+        let req = AuthRequest::new(Method::GET, "http://example.invalid", None);
+        retry.call(req).await?;
+
+        // GET is idempotent, so the default policy's 5xx retry applies.
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        Ok(())
+    }
+
+    /// Accepts a single connection, writes back `body` as a JSON response,
+    /// then closes. Returns the stub's `http://` base URL.
+    fn spawn_stub_server(body: &'static str) -> (String, std::thread::JoinHandle<()>) {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind stub listener");
+        let addr = listener.local_addr().expect("read stub listener addr");
+        let handle = std::thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes());
+                let _ = stream.shutdown(std::net::Shutdown::Both);
+            }
+        });
+        (format!("http://{}", addr), handle)
+    }
+
+    #[tokio::test]
+    async fn test_get_accounts_balance_against_local_stub() -> MyResult<()> {
+        let (base_url, server) = spawn_stub_server(r#"{"success":false,"error":"stub says no"}"#);
+
+        let client = DefaultClientBuilder::new("access-key", "secret-key")
+            .base_url(&base_url)
+            .build()?;
+
+        let err = client.get_accounts_balance().await.unwrap_err();
+        match err.downcast_ref::<crate::error::MyError>() {
+            Some(ResponseError { message, .. }) => assert_eq!(message, "stub says no"),
+            other => panic!("expected a ResponseError, got {:?}", other),
+        }
+
+        server.join().expect("stub server thread panicked");
+        Ok(())
+    }
 }